@@ -26,30 +26,53 @@ use nom::{
     IResult,
 };
 
-mod pyabcrab;
+pub mod midi;
+pub mod parser;
+pub mod temperament;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Accidental {
     Flat2,
     Flat,
+    HalfFlat,
     Natural,
+    HalfSharp,
     Sharp,
     Sharp2,
 }
 
+impl Accidental {
+    /// This accidental's offset in 12-EDO semitones, e.g. `0.5` for a
+    /// quarter-tone sharp. `Note::to_cents` does not use this; it places
+    /// accidentals on the `Temperament`'s generator chain instead.
+    pub fn semitone_offset(&self) -> f64 {
+        match self {
+            Accidental::Flat2 => -2.0,
+            Accidental::Flat => -1.0,
+            Accidental::HalfFlat => -0.5,
+            Accidental::Natural => 0.0,
+            Accidental::HalfSharp => 0.5,
+            Accidental::Sharp => 1.0,
+            Accidental::Sharp2 => 2.0,
+        }
+    }
+}
+
 impl Display for Accidental {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match *self {
             Accidental::Flat2 => f.write_str("𝄫"),
             Accidental::Flat => f.write_str("♭"),
+            Accidental::HalfFlat => f.write_str("𝄳"),
             Accidental::Natural => f.write_str("♮"),
+            Accidental::HalfSharp => f.write_str("𝄲"),
             Accidental::Sharp => f.write_str("♯"),
             Accidental::Sharp2 => f.write_str("𝄪"),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Note {
     octave: i32, // piano: 8 octaves, middle C is in the 4th octave
     key: char,
@@ -75,7 +98,7 @@ impl Display for Note {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NoteShape {
     Hundred28th,
     SixtyFourth,
@@ -105,7 +128,7 @@ impl Display for NoteShape {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Length {
     note_shape: NoteShape,
     dot: i32,
@@ -267,13 +290,99 @@ impl Display for Length {
     }
 }
 
+/// Something whose duration can be expressed as a count of 128th notes,
+/// the finest grain `Length::new` ever resolves to.
+pub trait KnownLength {
+    fn to_128th(&self) -> u32;
+}
+
+impl KnownLength for NoteShape {
+    fn to_128th(&self) -> u32 {
+        match self {
+            NoteShape::Hundred28th => 1,
+            NoteShape::SixtyFourth => 2,
+            NoteShape::ThirtySecond => 4,
+            NoteShape::Sixteenth => 8,
+            NoteShape::Eighth => 16,
+            NoteShape::Quarter => 32,
+            NoteShape::Half => 64,
+            NoteShape::Whole => 128,
+            NoteShape::Breve => 256,
+        }
+    }
+}
+
+impl KnownLength for Length {
+    fn to_128th(&self) -> u32 {
+        let base = self.note_shape.to_128th() as i32;
+        let dot_ratio = match self.dot {
+            0 => Rational32::new(1, 1),
+            1 => Rational32::new(3, 2),
+            2 => Rational32::new(7, 4),
+            3 => Rational32::new(15, 8),
+            _ => Rational32::new(1, 1),
+        };
+        (Rational32::from_integer(base) * dot_ratio).to_integer() as u32
+    }
+}
+
+impl Length {
+    /// The inverse of `to_128th`: rebuild a `Length` from a count of
+    /// 128th notes, e.g. `3` (a dotted 64th) round-trips back to itself.
+    pub fn from_128th(count: u32) -> Result<Length, IllegalLength> {
+        Length::new((count as i32, 128))
+    }
+
+    /// This length's duration as a fraction of a whole note, the same
+    /// unit `Length::new`'s ratio argument is given in.
+    pub fn duration(&self) -> Rational32 {
+        Rational32::new(self.to_128th() as i32, 128)
+    }
+}
+
+/// A beat group within a bar: either notes beamed together with no
+/// separating whitespace (ABC's "cd"), or a tuplet such as "(3abc"
+/// fitting its notes into a different amount of time than written.
+#[derive(Debug, PartialEq)]
+pub enum Group {
+    Beam(Vec<Note>),
+    Tuplet { ratio: Rational32, notes: Vec<Note> },
+}
+
+impl Group {
+    pub fn notes(&self) -> &[Note] {
+        match self {
+            Group::Beam(notes) => notes,
+            Group::Tuplet { notes, .. } => notes,
+        }
+    }
+}
+
+/// A measure: the beat groups it contains, plus whichever repeat markers
+/// (`:|`, `|:`) bracket it.
+#[derive(Debug, PartialEq)]
+pub struct Bar {
+    pub groups: Vec<Group>,
+    pub repeat_start: bool,
+    pub repeat_end: bool,
+}
+
+/// A full tune body, i.e. everything `parser::tune_body` can chew through
+/// in one pass: the bars making up the melody, in order.
+#[derive(Debug, PartialEq)]
+pub struct Tune {
+    pub bars: Vec<Bar>,
+}
+
 pub fn accidental(input: &str) -> IResult<&str, Accidental> {
     let flatflat = value(Accidental::Flat2, tag("__"));
+    let halfflat = value(Accidental::HalfFlat, tag("_/"));
     let flat = value(Accidental::Flat, tag("_"));
     let sharpsharp = value(Accidental::Sharp2, tag("^^"));
+    let halfsharp = value(Accidental::HalfSharp, tag("^/"));
     let sharp = value(Accidental::Sharp, tag("^"));
     let natural = value(Accidental::Natural, tag("="));
-    alt((sharpsharp, sharp, flatflat, flat, natural))(input)
+    alt((sharpsharp, halfsharp, sharp, flatflat, halfflat, flat, natural))(input)
 }
 
 pub fn octave_count(input: &str) -> IResult<&str, i32> {
@@ -373,3 +482,21 @@ fn parse_pitch() {
         ))
     );
 }
+
+#[test]
+fn length_to_128th_roundtrips() {
+    let quarter_double_dot = Length {
+        note_shape: NoteShape::Quarter,
+        dot: 2,
+    };
+    assert_eq!(quarter_double_dot.to_128th(), 56);
+    assert_eq!(Length::from_128th(56).unwrap(), quarter_double_dot);
+    assert_eq!(quarter_double_dot.duration(), Rational32::new(7, 16));
+
+    let whole = Length {
+        note_shape: NoteShape::Whole,
+        dot: 0,
+    };
+    assert_eq!(whole.to_128th(), 128);
+    assert_eq!(whole.duration(), Rational32::new(1, 1));
+}
@@ -0,0 +1,188 @@
+//! Turns a parsed [`Tune`] into a standard MIDI file, the way polyrhythmix's
+//! `create_smf`/`Part` path turns parsed rhythm into playback.
+
+use midly::{
+    num::{u15, u24, u28, u4, u7},
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, Track, TrackEvent, TrackEventKind,
+};
+
+use crate::{Accidental, KnownLength, Length, Note, Tune};
+#[cfg(test)]
+use crate::{Bar, Group, NoteShape};
+
+/// A quarter note is 32 128ths; `ppq` ticks span that same quarter, so one
+/// 128th is `ppq / 32` ticks.
+const HUNDRED28THS_PER_QUARTER: u32 = 32;
+
+fn pitch_class(key: char) -> i32 {
+    match key {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => unreachable!("Note::key is always A-G"),
+    }
+}
+
+fn ticks_for(length: &Length, ppq: u16) -> u32 {
+    length.to_128th() * ppq as u32 / HUNDRED28THS_PER_QUARTER
+}
+
+impl Note {
+    /// This note's MIDI note number: `12*(octave+1) + semitone`, where
+    /// `octave` is the crate's own numbering (middle C is octave 4).
+    /// Quarter-tone accidentals round to the nearest semitone, since
+    /// standard MIDI note numbers can't express them.
+    pub fn midi_number(&self) -> u8 {
+        let accidental = self
+            .accidental
+            .as_ref()
+            .map_or(0.0, Accidental::semitone_offset);
+        let semitone = pitch_class(self.key) as f64 + accidental;
+        (12 * (self.octave + 1) + semitone.round() as i32) as u8
+    }
+}
+
+impl Tune {
+    /// Render this tune as a single-track standard MIDI file at the given
+    /// tempo and pulses-per-quarter-note resolution.
+    pub fn to_smf(&self, tempo_bpm: u32, ppq: u16) -> Smf<'static> {
+        let mut track = Track::new();
+
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(60_000_000 / tempo_bpm))),
+        });
+
+        let channel = u4::new(0);
+        let velocity = u7::new(64);
+        for bar in &self.bars {
+            for group in &bar.groups {
+                for note in group.notes() {
+                    let key = u7::new(note.midi_number());
+                    let ticks = ticks_for(&note.length, ppq);
+
+                    track.push(TrackEvent {
+                        delta: u28::new(0),
+                        kind: TrackEventKind::Midi {
+                            channel,
+                            message: MidiMessage::NoteOn { key, vel: velocity },
+                        },
+                    });
+                    track.push(TrackEvent {
+                        delta: u28::new(ticks),
+                        kind: TrackEventKind::Midi {
+                            channel,
+                            message: MidiMessage::NoteOff { key, vel: velocity },
+                        },
+                    });
+                }
+            }
+        }
+
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+
+        Smf {
+            header: Header {
+                format: Format::SingleTrack,
+                timing: Timing::Metrical(u15::new(ppq)),
+            },
+            tracks: vec![track],
+        }
+    }
+}
+
+#[cfg(test)]
+fn note(key: char, note_shape: NoteShape) -> Note {
+    Note {
+        octave: 4,
+        key,
+        accidental: None,
+        length: Length {
+            note_shape,
+            dot: 0,
+        },
+    }
+}
+
+#[test]
+fn a4_is_midi_note_69() {
+    assert_eq!(note('A', NoteShape::Eighth).midi_number(), 69);
+}
+
+#[test]
+fn quarter_note_ticks_match_ppq() {
+    let quarter = Length {
+        note_shape: NoteShape::Quarter,
+        dot: 0,
+    };
+    assert_eq!(ticks_for(&quarter, 480), 480);
+}
+
+#[test]
+fn two_note_tune_emits_matched_note_on_off_pairs_in_order() {
+    let tune = Tune {
+        bars: vec![Bar {
+            groups: vec![Group::Beam(vec![
+                note('C', NoteShape::Quarter),
+                note('D', NoteShape::Quarter),
+            ])],
+            repeat_start: false,
+            repeat_end: false,
+        }],
+    };
+
+    let smf = tune.to_smf(120, 480);
+    let track = &smf.tracks[0];
+
+    let midi_events: Vec<MidiMessage> = track
+        .iter()
+        .filter_map(|event| match event.kind {
+            TrackEventKind::Midi { message, .. } => Some(message),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        midi_events,
+        vec![
+            MidiMessage::NoteOn {
+                key: u7::new(60),
+                vel: u7::new(64)
+            },
+            MidiMessage::NoteOff {
+                key: u7::new(60),
+                vel: u7::new(64)
+            },
+            MidiMessage::NoteOn {
+                key: u7::new(62),
+                vel: u7::new(64)
+            },
+            MidiMessage::NoteOff {
+                key: u7::new(62),
+                vel: u7::new(64)
+            },
+        ]
+    );
+
+    let note_off_delta = track
+        .iter()
+        .find(|event| {
+            matches!(
+                event.kind,
+                TrackEventKind::Midi {
+                    message: MidiMessage::NoteOff { .. },
+                    ..
+                }
+            )
+        })
+        .unwrap()
+        .delta;
+    assert_eq!(note_off_delta, u28::new(480));
+}
@@ -4,21 +4,26 @@ use nom::{
     character::complete::{digit1, satisfy},
     combinator::opt,
     combinator::{map, map_res, value},
+    error::{Error as NomError, ErrorKind},
     multi::fold_many0,
     multi::many1_count,
+    multi::{count, separated_list1},
     sequence::{preceded, tuple},
-    IResult,
+    Err as NomErr, IResult,
 };
+use num::rational::Rational32;
 
-use crate::{Accidental, Length, Note, NoteShape};
+use crate::{Accidental, Bar, Group, IllegalLength, Length, Note, NoteShape, Tune};
 
 fn accidental(input: &str) -> IResult<&str, Accidental> {
     let flatflat = value(Accidental::Flat2, tag("__"));
+    let halfflat = value(Accidental::HalfFlat, tag("_/"));
     let flat = value(Accidental::Flat, tag("_"));
     let sharpsharp = value(Accidental::Sharp2, tag("^^"));
+    let halfsharp = value(Accidental::HalfSharp, tag("^/"));
     let sharp = value(Accidental::Sharp, tag("^"));
     let natural = value(Accidental::Natural, tag("="));
-    alt((sharpsharp, sharp, flatflat, flat, natural))(input)
+    alt((sharpsharp, halfsharp, sharp, flatflat, halfflat, flat, natural))(input)
 }
 
 fn octave_count(input: &str) -> IResult<&str, i32> {
@@ -75,7 +80,197 @@ pub fn length(input: &str) -> IResult<&str, Length> {
     let slash_only = map(many1_count(tag("/")), |n| (1, base2.pow(n as u32)));
     let (rest, (numer, denom)) = alt((num_num, slash_num, slash_only))(input)?;
 
-    Ok((rest, Length::new((numer, denom)).unwrap()))
+    let length = Length::new((numer, denom)).map_err(|_| fail(input))?;
+    Ok((rest, length))
+}
+
+/// A single note token: an optional accidental, the pitch letter and its
+/// octave marks, and an optional explicit length (defaulting to an eighth
+/// note, same as bare `pitch`).
+pub fn note(input: &str) -> IResult<&str, Note> {
+    let mut octave = 4;
+
+    let low = satisfy(|ch| ('A'..='G').contains(&ch));
+    let high = satisfy(|ch| ('a'..='g').contains(&ch));
+
+    let high = map(high, |ch| {
+        octave += 1;
+        ch.to_ascii_uppercase()
+    });
+
+    let some_key = alt((high, low));
+
+    let (rest, (acc, key, mod_octave, len)) =
+        tuple((opt(accidental), some_key, octave_count, opt(length)))(input)?;
+
+    Ok((
+        rest,
+        Note {
+            octave: octave + mod_octave,
+            key,
+            accidental: acc,
+            length: len.unwrap_or(Length {
+                note_shape: NoteShape::Eighth,
+                dot: 0,
+            }),
+        },
+    ))
+}
+
+/// ABC broken rhythm: `>`/`<` dot one neighbor and halve the other, with
+/// the doubled form (`>>`/`<<`) applying the stronger 7/4-and-1/4 split.
+#[derive(Debug, Clone, Copy)]
+enum BrokenRhythm {
+    Gt,
+    GtGt,
+    Lt,
+    LtLt,
+}
+
+fn broken_rhythm_op(input: &str) -> IResult<&str, BrokenRhythm> {
+    alt((
+        value(BrokenRhythm::GtGt, tag(">>")),
+        value(BrokenRhythm::Gt, tag(">")),
+        value(BrokenRhythm::LtLt, tag("<<")),
+        value(BrokenRhythm::Lt, tag("<")),
+    ))(input)
+}
+
+fn scale_length(len: &Length, factor: Rational32) -> Result<Length, IllegalLength> {
+    let scaled = len.duration() * factor;
+    Length::new((*scaled.numer(), *scaled.denom()))
+}
+
+fn fail(input: &str) -> NomErr<NomError<&str>> {
+    NomErr::Failure(NomError::new(input, ErrorKind::Fail))
+}
+
+/// One or more notes glued together with no whitespace between them, i.e.
+/// a beamed group such as the "cd" in "B>cd BAG"; `>`/`<` broken rhythm
+/// between two notes is resolved here, dotting one neighbor and halving
+/// the other.
+pub fn beam(input: &str) -> IResult<&str, Group> {
+    let (mut rest, first) = note(input)?;
+    let mut notes = vec![first];
+
+    loop {
+        if let Ok((r, op)) = broken_rhythm_op(rest) {
+            let (r, next) = note(r)?;
+            let (strong, weak) = match op {
+                BrokenRhythm::Gt => (Rational32::new(3, 2), Rational32::new(1, 2)),
+                BrokenRhythm::GtGt => (Rational32::new(7, 4), Rational32::new(1, 4)),
+                BrokenRhythm::Lt => (Rational32::new(1, 2), Rational32::new(3, 2)),
+                BrokenRhythm::LtLt => (Rational32::new(1, 4), Rational32::new(7, 4)),
+            };
+            let prev = notes.last_mut().expect("group always has a first note");
+            prev.length = scale_length(&prev.length, strong).map_err(|_| fail(rest))?;
+            let mut next = next;
+            next.length = scale_length(&next.length, weak).map_err(|_| fail(rest))?;
+            notes.push(next);
+            rest = r;
+        } else if let Ok((r, next)) = note(rest) {
+            notes.push(next);
+            rest = r;
+        } else {
+            break;
+        }
+    }
+
+    Ok((rest, Group::Beam(notes)))
+}
+
+/// ABC tuplets: `(n` fits the following `n` notes into the time normally
+/// taken by `n-1`; the full `(p:q:r` form fits `p` notes into the time of
+/// `q`, with `r` (defaulting to `p`) notes following.
+pub fn tuplet(input: &str) -> IResult<&str, Group> {
+    let digits = map_res(digit1, |s: &str| s.parse::<i32>());
+    let (rest, (_, p, q, r)) = tuple((
+        tag("("),
+        digits,
+        opt(preceded(tag(":"), opt(map_res(digit1, |s: &str| s.parse::<i32>())))),
+        opt(preceded(tag(":"), opt(map_res(digit1, |s: &str| s.parse::<i32>())))),
+    ))(input)?;
+
+    let q = q.flatten().unwrap_or(p - 1);
+    let r = r.flatten().unwrap_or(p);
+    if p < 1 || q == 0 {
+        return Err(fail(input));
+    }
+    let ratio = Rational32::new(q, p);
+
+    let (rest, notes) = count(note, r as usize)(rest)?;
+    let mut scaled = Vec::with_capacity(notes.len());
+    for mut n in notes {
+        n.length = scale_length(&n.length, ratio).map_err(|_| fail(rest))?;
+        scaled.push(n);
+    }
+
+    Ok((rest, Group::Tuplet { ratio, notes: scaled }))
+}
+
+/// A single beat group: either a tuplet or a beamed run of notes.
+pub fn group(input: &str) -> IResult<&str, Group> {
+    alt((tuplet, beam))(input)
+}
+
+/// The `:|`/`|:`/`|` family of bar separators, split into which side (if
+/// any) carries a repeat colon.
+struct BarDelim {
+    repeat_end: bool,
+    repeat_start: bool,
+}
+
+fn bar_delim(input: &str) -> IResult<&str, BarDelim> {
+    map(
+        tuple((opt(tag(":")), tag("|"), opt(tag(":")))),
+        |(before, _, after)| BarDelim {
+            repeat_end: before.is_some(),
+            repeat_start: after.is_some(),
+        },
+    )(input)
+}
+
+/// A single bar: its beat groups separated by one or more spaces (tabs are
+/// not accepted as separators).
+pub fn bar(input: &str) -> IResult<&str, Bar> {
+    map(separated_list1(many1_count(tag(" ")), group), |groups| {
+        Bar {
+            groups,
+            repeat_start: false,
+            repeat_end: false,
+        }
+    })(input)
+}
+
+/// A whole tune body: bars separated by `|`, `|:` or `:|`, carrying
+/// whichever repeat markers bracket them.
+pub fn tune_body(input: &str) -> IResult<&str, Tune> {
+    let mut bars = Vec::new();
+    let mut rest = input;
+    let mut repeat_start = false;
+
+    loop {
+        let (r, mut this_bar) = bar(rest)?;
+        rest = r;
+
+        let (r, delim) = opt(bar_delim)(rest)?;
+        rest = r;
+
+        this_bar.repeat_start = repeat_start;
+        this_bar.repeat_end = delim.as_ref().is_some_and(|d| d.repeat_end);
+        bars.push(this_bar);
+
+        match delim {
+            Some(d) if !rest.is_empty() => {
+                repeat_start = d.repeat_start;
+                let (r, _) = opt(tag(" "))(rest)?;
+                rest = r;
+            }
+            _ => break,
+        }
+    }
+
+    Ok((rest, Tune { bars }))
 }
 
 const _TUNE1: &str = "B>cd BAG";
@@ -164,3 +359,154 @@ fn parse_length() {
         ))
     );
 }
+
+#[test]
+fn parse_group() {
+    let (rest, g) = group("cd BAG").unwrap();
+    assert_eq!(rest, " BAG");
+    assert_eq!(
+        g,
+        Group::Beam(vec![
+            Note {
+                octave: 5,
+                key: 'C',
+                accidental: None,
+                length: Length {
+                    note_shape: NoteShape::Eighth,
+                    dot: 0
+                },
+            },
+            Note {
+                octave: 5,
+                key: 'D',
+                accidental: None,
+                length: Length {
+                    note_shape: NoteShape::Eighth,
+                    dot: 0
+                },
+            },
+        ])
+    );
+}
+
+#[test]
+fn parse_broken_rhythm() {
+    let (rest, g) = group("B>cd").unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(
+        g.notes().to_vec(),
+        vec![
+            Note {
+                octave: 4,
+                key: 'B',
+                accidental: None,
+                length: Length {
+                    note_shape: NoteShape::Eighth,
+                    dot: 1
+                },
+            },
+            Note {
+                octave: 5,
+                key: 'C',
+                accidental: None,
+                length: Length {
+                    note_shape: NoteShape::Sixteenth,
+                    dot: 0
+                },
+            },
+            Note {
+                octave: 5,
+                key: 'D',
+                accidental: None,
+                length: Length {
+                    note_shape: NoteShape::Eighth,
+                    dot: 0
+                },
+            },
+        ]
+    );
+}
+
+#[test]
+fn broken_rhythm_needs_a_following_note() {
+    assert!(group("B>").is_err());
+}
+
+#[test]
+fn parse_bar() {
+    let (rest, b) = bar("FA Ac BA|").unwrap();
+    assert_eq!(rest, "|");
+    assert_eq!(b.groups.len(), 3);
+    assert_eq!(b.groups[0].notes().len(), 2);
+    assert_eq!(b.groups[1].notes().len(), 2);
+    assert_eq!(b.groups[2].notes().len(), 2);
+}
+
+#[test]
+fn parse_tuplet() {
+    // "(2:3:2" fits 2 notes into the time of 3, i.e. each note's duration
+    // is scaled by 3/2 -- a representable dotted eighth.
+    let (rest, g) = tuplet("(2:3:2ab").unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(
+        g,
+        Group::Tuplet {
+            ratio: Rational32::new(3, 2),
+            notes: vec![
+                Note {
+                    octave: 5,
+                    key: 'A',
+                    accidental: None,
+                    length: Length {
+                        note_shape: NoteShape::Eighth,
+                        dot: 1
+                    },
+                },
+                Note {
+                    octave: 5,
+                    key: 'B',
+                    accidental: None,
+                    length: Length {
+                        note_shape: NoteShape::Eighth,
+                        dot: 1
+                    },
+                },
+            ]
+        }
+    );
+}
+
+#[test]
+fn default_triplet_ratio_is_not_always_representable() {
+    // A bare "(3" scales each eighth note by 2/3, giving a twelfth note --
+    // not one of the dyadic/dotted durations `Length::new` can express.
+    assert!(group("(3abc").is_err());
+}
+
+#[test]
+fn zero_count_tuplet_errors_instead_of_panicking() {
+    assert!(tuplet("(0").is_err());
+    assert!(tuplet("(0ab").is_err());
+    assert!(tuplet("(0:5:2").is_err());
+}
+
+#[test]
+fn non_dyadic_explicit_length_errors_instead_of_panicking() {
+    // "3/5" is a syntactically valid explicit length, but 3/5 of a whole
+    // note isn't one of the dyadic/dotted durations `Length::new` can
+    // express.
+    assert!(length("3/5").is_err());
+    assert!(note("A3/5").is_err());
+    assert!(group("A3/5").is_err());
+    assert!(tune_body("A3/5").is_err());
+}
+
+#[test]
+fn parse_tune_body() {
+    let (rest, t) = tune_body(_TUNE2).unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(t.bars.len(), 4);
+    assert!(!t.bars[0].repeat_start);
+    assert!(!t.bars[0].repeat_end);
+    assert!(t.bars[3].repeat_end);
+}
@@ -0,0 +1,265 @@
+//! A rank-1 (period/generator) temperament engine, inspired by the `tune`
+//! crate's model of an equal division of the octave as a period plus a
+//! generating interval (e.g. the fifth).
+
+use crate::{Accidental, Length, Note, NoteShape};
+
+/// An equal division of the octave described by its period (steps per
+/// octave) and its generator (steps per generating interval, usually the
+/// fifth). 12-EDO is `{ period: 12, generator: 7 }`; 19-EDO is
+/// `{ period: 19, generator: 11 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Temperament {
+    pub period: u16,
+    pub generator: u16,
+}
+
+fn gcd(a: u16, b: u16) -> u16 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Extended Euclidean algorithm: returns `(gcd(a, b), x, y)` such that
+/// `a*x + b*y == gcd(a, b)`.
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x1, y1) = extended_gcd(b, a % b);
+        (g, y1, x1 - (a / b) * y1)
+    }
+}
+
+fn mod_inverse(a: i64, modulus: i64) -> Option<i64> {
+    if modulus == 1 {
+        return Some(0);
+    }
+    let (g, x, _) = extended_gcd(a.rem_euclid(modulus), modulus);
+    (g == 1).then(|| x.rem_euclid(modulus))
+}
+
+/// Standard order-of-sharps position of a diatonic letter relative to C:
+/// F is one fifth flatward, G one fifth sharpward, and so on around the
+/// circle of fifths.
+fn letter_chain_index(key: char) -> i64 {
+    match key {
+        'F' => -1,
+        'C' => 0,
+        'G' => 1,
+        'D' => 2,
+        'A' => 3,
+        'E' => 4,
+        'B' => 5,
+        _ => unreachable!("Note::key is always A-G"),
+    }
+}
+
+impl Temperament {
+    /// How many independent generator chains this temperament splits
+    /// into, e.g. 1 for 12-EDO and 19-EDO (their generators are coprime
+    /// with the period), more for degenerate cases like `{24, 6}`.
+    pub fn num_cycles(&self) -> u16 {
+        gcd(self.period, self.generator)
+    }
+
+    /// Where `index` generator-steps from the tonic actually sound: the
+    /// generator applied `index` times and reduced mod the period. This
+    /// is the un-folded pitch-class step, used for the sounding pitch.
+    pub fn generator_step(&self, index: i64) -> i64 {
+        let cycles = self.num_cycles() as i64;
+        let period = self.period as i64 / cycles;
+        let generator = (self.generator as i64 / cycles).rem_euclid(period);
+        let reduced_index = index.rem_euclid(period);
+        (generator * reduced_index).rem_euclid(period)
+    }
+
+    /// The chain position of scale step `index`: how many generators from
+    /// the tonic reach that step, folded to the range `(-period/2, period/2]`
+    /// so the sign tells you whether it lies sharpward or flatward. Found
+    /// by inverting the reduced generator modulo the reduced period, i.e.
+    /// the reverse direction of `generator_step`.
+    pub fn chain_degree(&self, index: i64) -> i64 {
+        let cycles = self.num_cycles() as i64;
+        let period = self.period as i64 / cycles;
+        let generator = (self.generator as i64 / cycles).rem_euclid(period);
+        let reduced_index = index.rem_euclid(period);
+        let inverse = mod_inverse(generator, period)
+            .expect("reduced generator and reduced period are coprime");
+        let degree = (inverse * reduced_index).rem_euclid(period);
+        if degree * 2 > period {
+            degree - period
+        } else {
+            degree
+        }
+    }
+
+    /// A step count's size in cents: `1200 * step / period`.
+    pub fn step_cents(&self, step: i64) -> f64 {
+        1200.0 * step as f64 / self.period as f64
+    }
+}
+
+impl Note {
+    /// This note's pitch in cents above middle C, located via the
+    /// temperament's generator chain rather than assuming 12 equal
+    /// semitones. A full sharp/flat moves 7 chain steps (a fifth's worth
+    /// of accidentals, the same convention as the circle of fifths);
+    /// quarter-tone accidentals add a flat 50 cents, since they split a
+    /// semitone evenly regardless of temperament. The accidental's chain
+    /// steps are turned into scale steps via `generator_step` and folded to
+    /// a signed delta *before* adding them to the letter's own (already
+    /// correctly placed) degree,
+    /// so e.g. B# correctly lands a full period above the tonic's C
+    /// instead of wrapping back down to it.
+    pub fn to_cents(&self, temperament: &Temperament) -> f64 {
+        let chain_steps = match self.accidental {
+            Some(Accidental::Sharp2) => 14,
+            Some(Accidental::Sharp) => 7,
+            Some(Accidental::Flat) => -7,
+            Some(Accidental::Flat2) => -14,
+            Some(Accidental::HalfSharp) | Some(Accidental::HalfFlat) | Some(Accidental::Natural)
+            | None => 0,
+        };
+        let quarter_tone_cents = match self.accidental {
+            Some(Accidental::HalfSharp) => 50.0,
+            Some(Accidental::HalfFlat) => -50.0,
+            _ => 0.0,
+        };
+
+        let letter_degree = temperament.generator_step(letter_chain_index(self.key));
+        let reduced_period = temperament.period as i64 / temperament.num_cycles() as i64;
+        let accidental_step = temperament.generator_step(chain_steps);
+        let accidental_delta = if accidental_step * 2 > reduced_period {
+            accidental_step - reduced_period
+        } else {
+            accidental_step
+        };
+        let step = letter_degree
+            + accidental_delta
+            + (self.octave - 4) as i64 * temperament.period as i64;
+
+        temperament.step_cents(step) + quarter_tone_cents
+    }
+
+    /// This note's frequency in a given temperament, tuned against
+    /// `a4_hz`: `a4_hz * 2^(cents_from_a4 / 1200)`.
+    pub fn to_frequency(&self, temperament: &Temperament, a4_hz: f64) -> f64 {
+        let a4 = Note {
+            octave: 4,
+            key: 'A',
+            accidental: None,
+            length: Length {
+                note_shape: NoteShape::Eighth,
+                dot: 0,
+            },
+        };
+        let cents_from_a4 = self.to_cents(temperament) - a4.to_cents(temperament);
+        a4_hz * 2f64.powf(cents_from_a4 / 1200.0)
+    }
+}
+
+#[test]
+fn twelve_edo_a4_is_440hz() {
+    let twelve_edo = Temperament {
+        period: 12,
+        generator: 7,
+    };
+    let a4 = Note {
+        octave: 4,
+        key: 'A',
+        accidental: None,
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+    assert!((a4.to_frequency(&twelve_edo, 440.0) - 440.0).abs() < 1e-9);
+
+    let a5 = Note {
+        octave: 5,
+        key: 'A',
+        accidental: None,
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+    assert!((a5.to_frequency(&twelve_edo, 440.0) - 880.0).abs() < 1e-9);
+}
+
+#[test]
+fn twelve_edo_c_major_scale_is_monotonic_within_an_octave() {
+    let twelve_edo = Temperament {
+        period: 12,
+        generator: 7,
+    };
+    let note = |key| Note {
+        octave: 4,
+        key,
+        accidental: None,
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+
+    let freqs: Vec<f64> = ['C', 'D', 'E', 'F', 'G', 'A', 'B']
+        .iter()
+        .map(|&key| note(key).to_frequency(&twelve_edo, 440.0))
+        .collect();
+
+    for pair in freqs.windows(2) {
+        assert!(pair[0] < pair[1], "scale should ascend: {:?}", freqs);
+    }
+}
+
+#[test]
+fn accidentals_that_cross_the_octave_boundary_land_in_the_right_octave() {
+    let twelve_edo = Temperament {
+        period: 12,
+        generator: 7,
+    };
+
+    let b_sharp = Note {
+        octave: 4,
+        key: 'B',
+        accidental: Some(Accidental::Sharp),
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+    let c5 = Note {
+        octave: 5,
+        key: 'C',
+        accidental: None,
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+    assert!((b_sharp.to_cents(&twelve_edo) - c5.to_cents(&twelve_edo)).abs() < 1e-9);
+
+    let c_flat = Note {
+        octave: 4,
+        key: 'C',
+        accidental: Some(Accidental::Flat),
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+    let b3 = Note {
+        octave: 3,
+        key: 'B',
+        accidental: None,
+        length: Length {
+            note_shape: NoteShape::Eighth,
+            dot: 0,
+        },
+    };
+    assert!((c_flat.to_cents(&twelve_edo) - b3.to_cents(&twelve_edo)).abs() < 1e-9);
+}